@@ -0,0 +1,159 @@
+/*!
+#  Address Lists
+
+A [MailBox] represents a single `name <local@domain>`. The address-bearing header
+fields (`From`, `To`, `Cc`, `Bcc`, `Reply-To`, ...) are *address-lists*, which may in
+addition contain RFC 5322 groups of the form `display-name: mailbox, mailbox;`.
+
+[Address] models one element of such a list and [AddressList] the whole field.
+
+Defined in [RFC 5322 Section 3.4](https://tools.ietf.org/html/rfc5322#section-3.4).
+ */
+use std::{fmt::Display, str::FromStr};
+
+use chumsky::{error::Cheap, prelude::*};
+use thiserror::Error;
+
+use crate::{parsers::rfcs::rfc5322::mailbox, MailBox};
+
+/// Write a [MailBox] in its address-list wire form: `name <email>` when a display
+/// name is present, otherwise the bare address. [MailBox]'s own [Display] prints only
+/// the display name, which is lossy for re-emitting a header value, so address-lists
+/// use the same form as its serde [Serialize](serde::Serialize) impl.
+fn write_mailbox(f: &mut std::fmt::Formatter<'_>, mailbox: &MailBox) -> std::fmt::Result {
+    match &mailbox.name {
+        Some(name) => write!(f, "{} <{}>", name, mailbox.email),
+        None => write!(f, "{}", mailbox.email),
+    }
+}
+
+/// A single entry in an [AddressList]: either a bare [MailBox] or a named group.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Address {
+    /// A single mailbox.
+    Mailbox(MailBox),
+    /// A named group of mailboxes (`display-name: member, member;`).
+    Group { name: String, members: Vec<MailBox> },
+}
+impl Display for Address {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Address::Mailbox(mailbox) => write_mailbox(f, mailbox),
+            Address::Group { name, members } => {
+                write!(f, "{}:", name)?;
+                for (index, member) in members.iter().enumerate() {
+                    if index > 0 {
+                        f.write_str(",")?;
+                    }
+                    f.write_str(" ")?;
+                    write_mailbox(f, member)?;
+                }
+                f.write_str(";")
+            }
+        }
+    }
+}
+
+/// A parsed address-list header value.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct AddressList(pub Vec<Address>);
+impl AddressList {
+    /// Iterate over every [MailBox] in the list, flattening groups into their members.
+    pub fn all_mailboxes(&self) -> impl Iterator<Item = &MailBox> {
+        self.0.iter().flat_map(|address| match address {
+            Address::Mailbox(mailbox) => std::slice::from_ref(mailbox).iter(),
+            Address::Group { members, .. } => members.iter(),
+        })
+    }
+}
+impl Display for AddressList {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        for (index, address) in self.0.iter().enumerate() {
+            if index > 0 {
+                f.write_str(", ")?;
+            }
+            write!(f, "{}", address)?;
+        }
+        Ok(())
+    }
+}
+
+/// The chumsky combinator for an address-list, reusing [`mailbox`] for each mailbox.
+pub(crate) fn address_list<'a>(
+) -> impl Parser<'a, &'a str, Vec<Address>, extra::Err<Cheap>> + Clone {
+    let mailbox_list = mailbox()
+        .map(MailBox::from)
+        .separated_by(just(',').padded())
+        .at_least(1)
+        .collect::<Vec<_>>();
+
+    // A group is `display-name ':' [mailbox-list] ';'`.
+    let display_name = none_of(":,;")
+        .repeated()
+        .at_least(1)
+        .collect::<String>()
+        .map(|name| name.trim().to_owned());
+    let group = display_name
+        .then_ignore(just(':').padded())
+        .then(mailbox_list.clone().or_not())
+        .then_ignore(just(';').padded())
+        .map(|(name, members)| Address::Group {
+            name,
+            members: members.unwrap_or_default(),
+        });
+
+    let single = mailbox().map(|raw| Address::Mailbox(MailBox::from(raw)));
+
+    group
+        .or(single)
+        .separated_by(just(',').padded())
+        .at_least(1)
+        .collect::<Vec<_>>()
+        .padded()
+}
+
+/// An error that occurs when parsing an [AddressList].
+#[derive(Debug, Clone, PartialEq, Hash, Error)]
+pub struct InvalidAddressList {
+    /// The spans that caused the error
+    pub spans: Vec<Cheap>,
+    /// The context of the error
+    pub ctx: Option<String>,
+}
+impl Display for InvalidAddressList {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "Invalid AddressList")?;
+        if let Some(context) = self.ctx.as_ref() {
+            writeln!(f, "Context: {}", context)?;
+            for span in &self.spans {
+                writeln!(f, "    {}", span)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl FromStr for AddressList {
+    type Err = InvalidAddressList;
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match address_list().parse(value).into_result() {
+            Ok(addresses) => Ok(AddressList(addresses)),
+            Err(spans) => Err(InvalidAddressList {
+                spans,
+                ctx: Some(value.to_owned()),
+            }),
+        }
+    }
+}
+impl<'a> TryFrom<&'a str> for AddressList {
+    type Error = InvalidAddressList;
+    fn try_from(value: &'a str) -> Result<Self, Self::Error> {
+        value.parse()
+    }
+}
+
+impl From<MailBox> for Address {
+    fn from(value: MailBox) -> Self {
+        Address::Mailbox(value)
+    }
+}
@@ -108,6 +108,52 @@ impl MailBox {
     pub fn into_inner(self) -> (Option<String>, EmailAddress) {
         (self.name, self.email)
     }
+    /// The detail (subaddress) portion of the local part, using the default `+`
+    /// separator.
+    ///
+    /// For `user+tag@example.com` this returns `Some("tag")`; for `user@example.com`
+    /// it returns `None`.
+    pub fn subaddress(&self) -> Option<&str> {
+        self.subaddress_with(SubaddressConfig::default())
+    }
+    /// The detail portion of the local part, split on `config.separator`.
+    ///
+    /// Only the first occurrence of the separator is significant; everything after
+    /// it is the tag.
+    pub fn subaddress_with(&self, config: SubaddressConfig) -> Option<&str> {
+        self.get_local()
+            .split_once(config.separator)
+            .map(|(_, tag)| tag)
+    }
+    /// The local part with any subaddress removed, using the default `+` separator.
+    ///
+    /// For `user+tag@example.com` this returns `user`.
+    pub fn base_local(&self) -> &str {
+        self.base_local_with(SubaddressConfig::default())
+    }
+    /// The local part with any subaddress removed, split on `config.separator`.
+    pub fn base_local_with(&self, config: SubaddressConfig) -> &str {
+        match self.get_local().split_once(config.separator) {
+            Some((base, _)) => base,
+            None => self.get_local(),
+        }
+    }
+}
+/// Configuration for [subaddressing] / plus-addressing.
+///
+/// Most deployments use `+` as the detail separator; some use `-`. The separator is
+/// applied to the first occurrence only, leaving the remainder as the tag.
+///
+/// [subaddressing]: https://datatracker.ietf.org/doc/html/rfc5233
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SubaddressConfig {
+    /// The character that separates the base local part from the detail.
+    pub separator: char,
+}
+impl Default for SubaddressConfig {
+    fn default() -> Self {
+        Self { separator: '+' }
+    }
 }
 #[cfg(feature = "serde")]
 mod _serde {
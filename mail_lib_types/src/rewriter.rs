@@ -0,0 +1,65 @@
+/*!
+#  Address Rewriting
+
+Routing frequently needs to canonicalise an address before a lookup: normalising a
+catch-all `*@domain` to a fixed mailbox, or stripping a [subaddress] before matching
+against a user table.
+
+[AddressRewriter] captures that transform. [RegexRewriter] is a regex-backed
+implementation driven by a pattern/replacement pair.
+
+The invariant enforced by every rewriter: the display name is preserved and the
+result is always a valid [EmailAddress] — a rewrite that would produce an invalid
+address is surfaced as an error rather than returned.
+
+[subaddress]: crate::mail_box::SubaddressConfig
+ */
+use regex::Regex;
+use thiserror::Error;
+
+use crate::{EmailAddress, MailBox};
+
+/// A transform over the address of a [MailBox].
+pub trait AddressRewriter {
+    /// Rewrite `mailbox`, preserving its display name.
+    fn rewrite(&self, mailbox: &MailBox) -> Result<MailBox, RewriteError>;
+}
+
+/// An error produced while rewriting a [MailBox].
+#[derive(Debug, Clone, PartialEq, Eq, Error)]
+pub enum RewriteError {
+    /// The rewrite produced a string that is not a valid address.
+    #[error("rewrite produced an invalid address: {0}")]
+    InvalidAddress(String),
+}
+
+/// A [AddressRewriter] that applies a [Regex] replacement to the address.
+///
+/// The replacement runs against the bare `local@domain` form (the display name is
+/// copied across unchanged).
+#[derive(Debug, Clone)]
+pub struct RegexRewriter {
+    /// The pattern matched against the address.
+    pub pattern: Regex,
+    /// The replacement, supporting the usual `$1`/`${name}` capture references.
+    pub replacement: String,
+}
+impl RegexRewriter {
+    /// Create a new [RegexRewriter] from a pattern and replacement.
+    pub fn new(pattern: Regex, replacement: impl Into<String>) -> Self {
+        Self {
+            pattern,
+            replacement: replacement.into(),
+        }
+    }
+}
+impl AddressRewriter for RegexRewriter {
+    fn rewrite(&self, mailbox: &MailBox) -> Result<MailBox, RewriteError> {
+        let rewritten = self
+            .pattern
+            .replace(mailbox.email.as_ref(), self.replacement.as_str());
+        let email = EmailAddress::new(rewritten.as_ref())
+            .map_err(|_| RewriteError::InvalidAddress(rewritten.into_owned()))?;
+        Ok(MailBox::new(mailbox.name.clone(), email))
+    }
+}
@@ -0,0 +1,171 @@
+/*!
+# Connection State Machine
+
+[SMTPConnectionState] tracks where a session sits in the ESMTP command sequence. On
+its own it is just a tag; [`SMTPConnectionState::transition`] turns it into an
+enforceable protocol engine by deciding, for a given [Command], whether that command
+is legal from the current state and what the resulting state is.
+
+The happy path is:
+
+```text
+Connected --EHLO/HELO--> Greeted --MAIL--> MailTransaction --RCPT--> Recipients --DATA--> Data
+              ^                                                                            |
+              +----------------------------- (message accepted) --------------------------+
+```
+
+`AUTH` moves `Greeted` to `Authenticated`, which behaves like `Greeted` for the
+purpose of starting a mail transaction. `RSET` abandons any in-progress transaction
+and returns to `Greeted`; before greeting it leaves the state at `Connected`.
+*/
+use crate::command::Command;
+use crate::error::SMTPError;
+use thiserror::Error;
+
+/// The position of an [SMTPConnection](crate::smtp_server::SMTPConnection) within the
+/// ESMTP command sequence.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SMTPConnectionState {
+    /// The transport is open but the client has not yet issued `EHLO`/`HELO`.
+    Connected,
+    /// The client has greeted the server and no transaction is in progress.
+    Greeted,
+    /// The client has authenticated (`AUTH`); equivalent to [Greeted](Self::Greeted)
+    /// for starting a transaction.
+    Authenticated,
+    /// A `MAIL FROM` has been accepted but no recipients have been named yet.
+    MailTransaction,
+    /// At least one `RCPT TO` has been accepted.
+    Recipients,
+    /// `DATA`/`BDAT` has been accepted; message content is being transferred.
+    Data,
+}
+
+/// A command issued out of sequence. Maps to the SMTP 503 "bad sequence of commands"
+/// reply.
+#[derive(Debug, Clone, PartialEq, Eq, Error)]
+#[error("503 bad sequence of commands: {command} not permitted in state {state:?}")]
+pub struct BadSequence {
+    /// The offending command verb.
+    pub command: &'static str,
+    /// The state the connection was in when the command arrived.
+    pub state: SMTPConnectionState,
+}
+impl From<BadSequence> for SMTPError {
+    fn from(value: BadSequence) -> Self {
+        SMTPError::BadSequenceOfCommands(value.to_string())
+    }
+}
+
+impl SMTPConnectionState {
+    /// Validate `command` against this state, returning the state to move to.
+    ///
+    /// Out-of-order commands (`RCPT` before `MAIL`, `DATA` with no recipients, `MAIL`
+    /// before greeting, ...) are rejected with a [BadSequence] error. Commands that do
+    /// not advance the transaction (`NOOP`, `VRFY`, `EXPN`, `HELP`, `QUIT`) are always
+    /// legal and leave the state unchanged.
+    pub fn transition(self, command: &Command) -> Result<SMTPConnectionState, BadSequence> {
+        use SMTPConnectionState::*;
+        match command {
+            // Greeting (re-)identifies the client and resets any transaction.
+            Command::Helo { .. } => Ok(Greeted),
+            Command::Auth { .. } => match self {
+                Greeted | Authenticated => Ok(Authenticated),
+                _ => Err(self.bad("AUTH")),
+            },
+            Command::Mail { .. } => match self {
+                Greeted | Authenticated => Ok(MailTransaction),
+                _ => Err(self.bad("MAIL")),
+            },
+            Command::Rcpt { .. } => match self {
+                MailTransaction | Recipients => Ok(Recipients),
+                _ => Err(self.bad("RCPT")),
+            },
+            Command::Data => match self {
+                // DATA requires at least one accepted recipient.
+                Recipients => Ok(Data),
+                _ => Err(self.bad("DATA")),
+            },
+            Command::Bdat { .. } => match self {
+                Recipients | Data => Ok(Data),
+                _ => Err(self.bad("BDAT")),
+            },
+            // RSET abandons the transaction and returns to the greeted state, except
+            // before greeting where there is nothing to reset.
+            Command::Rset => match self {
+                Connected => Ok(Connected),
+                _ => Ok(Greeted),
+            },
+            // Session commands that never depend on, or change, the transaction state.
+            Command::Noop
+            | Command::Vrfy(_)
+            | Command::Expn(_)
+            | Command::Help(_)
+            | Command::Quit => Ok(self),
+        }
+    }
+
+    fn bad(self, command: &'static str) -> BadSequence {
+        BadSequence {
+            command,
+            state: self,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::SMTPConnectionState::{self, *};
+    use crate::command::{Command, DomainOrAddress};
+    use mail_lib_types::MailBox;
+
+    fn helo() -> Command {
+        Command::Helo {
+            extended: true,
+            domain_or_address: DomainOrAddress::Domain("mail.example.com".to_owned()),
+        }
+    }
+    fn mail() -> Command {
+        Command::Mail {
+            reverse_path: None,
+            parameters: Vec::new(),
+        }
+    }
+    fn rcpt() -> Command {
+        Command::Rcpt {
+            forward_path: MailBox::try_from("bob@example.com").unwrap(),
+            parameters: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn happy_path_walks_to_data() {
+        let state = Connected;
+        let state = state.transition(&helo()).unwrap();
+        assert_eq!(state, Greeted);
+        let state = state.transition(&mail()).unwrap();
+        assert_eq!(state, MailTransaction);
+        let state = state.transition(&rcpt()).unwrap();
+        assert_eq!(state, Recipients);
+        // A second recipient stays in Recipients.
+        let state = state.transition(&rcpt()).unwrap();
+        assert_eq!(state, Recipients);
+        let state = state.transition(&Command::Data).unwrap();
+        assert_eq!(state, Data);
+    }
+
+    #[test]
+    fn out_of_order_commands_are_rejected() {
+        assert!(Connected.transition(&mail()).is_err());
+        assert!(Greeted.transition(&rcpt()).is_err());
+        assert!(MailTransaction.transition(&Command::Data).is_err());
+    }
+
+    #[test]
+    fn rset_returns_to_greeted() {
+        let state = SMTPConnectionState::Recipients;
+        assert_eq!(state.transition(&Command::Rset).unwrap(), Greeted);
+        // Nothing to reset before greeting.
+        assert_eq!(Connected.transition(&Command::Rset).unwrap(), Connected);
+    }
+}
@@ -0,0 +1,359 @@
+/*!
+# Milter Client
+
+A [milter] is an out-of-process content filter that a server consults at the ESMTP
+transaction checkpoints (`connect`, `helo`, `mail`, `rcpt`, `data`, per-header,
+end-of-headers, body chunks, end-of-body). This module implements the client side of
+the Sendmail milter wire protocol so an
+[SMTPConnection](crate::smtp_server::SMTPConnection) can delegate filtering decisions.
+
+Every packet on the wire is a 4-byte big-endian length followed by a 1-byte command
+code and a payload; the length counts the command byte and the payload. A session
+opens with an [`SMFIC_OPTNEG`](protocol::SMFIC_OPTNEG) handshake that negotiates the
+protocol version, the set of actions the client will honour, and the set of protocol
+steps the filter wants to receive.
+
+[milter]: https://pythonhosted.org/pymilter/milter_api/index.html
+*/
+use crate::command::Command;
+use crate::error::SMTPError;
+use mail_lib_types::MailBox;
+use thiserror::Error;
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt};
+
+pub mod protocol;
+
+use protocol::*;
+
+/// The decision returned by a milter at a transaction checkpoint, plus the message
+/// modifications a filter may request.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum MilterAction {
+    /// Continue processing the message.
+    Continue,
+    /// Accept the message without consulting the filter further.
+    Accept,
+    /// Reject with a permanent (5xx) failure.
+    Reject,
+    /// Silently discard the message (acknowledged as accepted to the client).
+    Discard,
+    /// Reject with a transient (4xx) failure.
+    TempFail,
+    /// Replace the message body with the supplied bytes.
+    ReplaceBody(Vec<u8>),
+    /// Add a header.
+    AddHeader { name: String, value: String },
+    /// Change the envelope sender.
+    ChangeFrom(MailBox),
+    /// Add an envelope recipient.
+    AddRcpt(MailBox),
+}
+impl MilterAction {
+    /// Map a checkpoint decision onto the error a server should return to the client.
+    ///
+    /// A [`Reject`](Self::Reject) becomes a permanent 550 and a
+    /// [`TempFail`](Self::TempFail) a transient 451, so that, for example, a reject at
+    /// the `rcpt` stage is surfaced before `DATA` is ever accepted. Any other action
+    /// is returned unchanged for the caller to apply.
+    pub fn enforce(self) -> Result<MilterAction, SMTPError> {
+        match self {
+            MilterAction::Reject => Err(SMTPError::Rejected(550, "5.7.1 Rejected by filter".into())),
+            MilterAction::TempFail => Err(SMTPError::Rejected(
+                451,
+                "4.7.1 Temporary failure from filter".into(),
+            )),
+            other => Ok(other),
+        }
+    }
+}
+
+/// An error produced while talking to a milter.
+#[derive(Debug, Error)]
+pub enum MilterError {
+    #[error("milter transport error: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("milter packet exceeded the maximum length of {MAX_PACKET_LEN} bytes")]
+    PacketTooLarge,
+    #[error("milter sent an empty packet")]
+    EmptyPacket,
+    #[error("unexpected milter response code: {0:?}")]
+    UnexpectedResponse(char),
+    #[error("malformed milter payload")]
+    MalformedPayload,
+}
+
+/// A client connection to a milter filter.
+#[derive(Debug)]
+pub struct MilterClient<S> {
+    stream: S,
+    /// The protocol version negotiated with the filter.
+    version: u32,
+    /// The action bitmask the client offered (`SMFIF_*`), narrowed after negotiation
+    /// to the actions the filter also requested.
+    actions: u32,
+    /// The protocol-step bitmask the filter requested (`SMFIP_*`).
+    protocol: u32,
+}
+
+impl<S> MilterClient<S>
+where
+    S: AsyncRead + AsyncWrite + Unpin,
+{
+    /// Wrap a transport without negotiating; used mostly by tests and callers that
+    /// negotiate manually.
+    pub fn new(stream: S) -> Self {
+        Self {
+            stream,
+            version: SMFI_PROT_VERSION,
+            actions: SMFIF_DEFAULT,
+            protocol: 0,
+        }
+    }
+
+    /// Perform the `SMFIC_OPTNEG` handshake, offering `actions` and requesting
+    /// version [`SMFI_PROT_VERSION`].
+    pub async fn negotiate(&mut self, actions: u32) -> Result<(), MilterError> {
+        self.actions = actions;
+        let mut payload = Vec::with_capacity(12);
+        payload.extend_from_slice(&SMFI_PROT_VERSION.to_be_bytes());
+        payload.extend_from_slice(&actions.to_be_bytes());
+        payload.extend_from_slice(&0u32.to_be_bytes());
+        self.write_packet(SMFIC_OPTNEG, &payload).await?;
+
+        let (code, payload) = self.read_packet().await?;
+        if code != SMFIC_OPTNEG || payload.len() < 12 {
+            return Err(MilterError::MalformedPayload);
+        }
+        self.version = u32::from_be_bytes([payload[0], payload[1], payload[2], payload[3]]);
+        self.actions &= u32::from_be_bytes([payload[4], payload[5], payload[6], payload[7]]);
+        self.protocol = u32::from_be_bytes([payload[8], payload[9], payload[10], payload[11]]);
+        Ok(())
+    }
+
+    /// Whether the filter asked to skip the given protocol step (`SMFIP_NO*`).
+    fn skips(&self, step: u32) -> bool {
+        self.protocol & step != 0
+    }
+
+    /// `SMFIC_CONNECT` — the client connected from `hostname`/`address`.
+    pub async fn connect(
+        &mut self,
+        hostname: &str,
+        address: &str,
+    ) -> Result<MilterAction, MilterError> {
+        if self.skips(SMFIP_NOCONNECT) {
+            return Ok(MilterAction::Continue);
+        }
+        let mut payload = Vec::new();
+        push_cstr(&mut payload, hostname);
+        payload.push(b'4');
+        payload.extend_from_slice(&0u16.to_be_bytes());
+        push_cstr(&mut payload, address);
+        self.command(SMFIC_CONNECT, &payload).await
+    }
+
+    /// `SMFIC_HELO` — the client greeted with `domain`.
+    pub async fn helo(&mut self, domain: &str) -> Result<MilterAction, MilterError> {
+        if self.skips(SMFIP_NOHELO) {
+            return Ok(MilterAction::Continue);
+        }
+        let mut payload = Vec::new();
+        push_cstr(&mut payload, domain);
+        self.command(SMFIC_HELO, &payload).await
+    }
+
+    /// `SMFIC_MAIL` — `MAIL FROM` with its ESMTP arguments.
+    pub async fn mail(
+        &mut self,
+        from: &MailBox,
+        args: &[String],
+    ) -> Result<MilterAction, MilterError> {
+        if self.skips(SMFIP_NOMAIL) {
+            return Ok(MilterAction::Continue);
+        }
+        self.command(SMFIC_MAIL, &path_payload(from, args)).await
+    }
+
+    /// `SMFIC_RCPT` — `RCPT TO` with its ESMTP arguments.
+    pub async fn rcpt(
+        &mut self,
+        to: &MailBox,
+        args: &[String],
+    ) -> Result<MilterAction, MilterError> {
+        if self.skips(SMFIP_NORCPT) {
+            return Ok(MilterAction::Continue);
+        }
+        self.command(SMFIC_RCPT, &path_payload(to, args)).await
+    }
+
+    /// `SMFIC_DATA` — the client issued `DATA`.
+    pub async fn data(&mut self) -> Result<MilterAction, MilterError> {
+        self.command(SMFIC_DATA, &[]).await
+    }
+
+    /// Consult the filter at the transaction checkpoint implied by `command` and fold
+    /// the decision into the SMTP state machine.
+    ///
+    /// `MAIL`, `RCPT`, and `DATA`/`BDAT` are dispatched to their milter checkpoints
+    /// ([`mail`](Self::mail)/[`rcpt`](Self::rcpt)/[`data`](Self::data)); every other
+    /// verb (and a null reverse-path) is a no-op that yields
+    /// [`Continue`](MilterAction::Continue). The decision is run through
+    /// [`MilterAction::enforce`], so a `Reject` from the `rcpt` stage becomes a 550
+    /// before `DATA` is ever accepted. A transport failure is reported as a transient
+    /// 451 so a broken filter fails closed rather than silently passing mail.
+    pub async fn check(&mut self, command: &Command) -> Result<MilterAction, SMTPError> {
+        let action = match command {
+            Command::Mail {
+                reverse_path: Some(from),
+                parameters,
+            } => {
+                let args: Vec<String> = parameters.iter().map(|p| p.to_string()).collect();
+                self.mail(from, &args).await
+            }
+            Command::Rcpt {
+                forward_path,
+                parameters,
+            } => {
+                let args: Vec<String> = parameters.iter().map(|p| p.to_string()).collect();
+                self.rcpt(forward_path, &args).await
+            }
+            Command::Data | Command::Bdat { .. } => self.data().await,
+            _ => Ok(MilterAction::Continue),
+        }
+        .map_err(|err| SMTPError::Rejected(451, format!("4.7.1 filter unavailable: {err}")))?;
+        action.enforce()
+    }
+
+    /// `SMFIC_HEADER` — one message header.
+    pub async fn header(&mut self, name: &str, value: &str) -> Result<MilterAction, MilterError> {
+        if self.skips(SMFIP_NOHDRS) {
+            return Ok(MilterAction::Continue);
+        }
+        let mut payload = Vec::new();
+        push_cstr(&mut payload, name);
+        push_cstr(&mut payload, value);
+        self.command(SMFIC_HEADER, &payload).await
+    }
+
+    /// `SMFIC_EOH` — end of headers.
+    pub async fn end_of_headers(&mut self) -> Result<MilterAction, MilterError> {
+        if self.skips(SMFIP_NOEOH) {
+            return Ok(MilterAction::Continue);
+        }
+        self.command(SMFIC_EOH, &[]).await
+    }
+
+    /// `SMFIC_BODY` — one body chunk.
+    pub async fn body(&mut self, chunk: &[u8]) -> Result<MilterAction, MilterError> {
+        if self.skips(SMFIP_NOBODY) {
+            return Ok(MilterAction::Continue);
+        }
+        self.command(SMFIC_BODY, chunk).await
+    }
+
+    /// `SMFIC_BODYEOB` — end of body; this is where filters emit modifications.
+    pub async fn end_of_body(&mut self) -> Result<MilterAction, MilterError> {
+        self.command(SMFIC_BODYEOB, &[]).await
+    }
+
+    /// Send a command packet and interpret the filter's reply.
+    async fn command(&mut self, code: u8, payload: &[u8]) -> Result<MilterAction, MilterError> {
+        self.write_packet(code, payload).await?;
+        loop {
+            let (reply, body) = self.read_packet().await?;
+            // Progress keep-alives do not carry a decision; keep reading.
+            if reply == SMFIR_PROGRESS {
+                continue;
+            }
+            return parse_action(reply, &body);
+        }
+    }
+
+    async fn write_packet(&mut self, code: u8, payload: &[u8]) -> Result<(), MilterError> {
+        let len = (payload.len() + 1) as u32;
+        self.stream.write_all(&len.to_be_bytes()).await?;
+        self.stream.write_all(&[code]).await?;
+        self.stream.write_all(payload).await?;
+        self.stream.flush().await?;
+        Ok(())
+    }
+
+    async fn read_packet(&mut self) -> Result<(u8, Vec<u8>), MilterError> {
+        let mut len_buf = [0u8; 4];
+        self.stream.read_exact(&mut len_buf).await?;
+        let len = u32::from_be_bytes(len_buf) as usize;
+        if len == 0 {
+            return Err(MilterError::EmptyPacket);
+        }
+        if len > MAX_PACKET_LEN {
+            return Err(MilterError::PacketTooLarge);
+        }
+        let mut code = [0u8; 1];
+        self.stream.read_exact(&mut code).await?;
+        let mut payload = vec![0u8; len - 1];
+        self.stream.read_exact(&mut payload).await?;
+        Ok((code[0], payload))
+    }
+}
+
+/// Interpret a milter response code and payload as a [MilterAction].
+fn parse_action(code: u8, payload: &[u8]) -> Result<MilterAction, MilterError> {
+    match code {
+        SMFIR_CONTINUE => Ok(MilterAction::Continue),
+        SMFIR_ACCEPT => Ok(MilterAction::Accept),
+        SMFIR_REJECT | SMFIR_REPLYCODE => Ok(MilterAction::Reject),
+        SMFIR_DISCARD => Ok(MilterAction::Discard),
+        SMFIR_TEMPFAIL => Ok(MilterAction::TempFail),
+        SMFIR_REPLBODY => Ok(MilterAction::ReplaceBody(payload.to_vec())),
+        SMFIR_ADDHEADER => {
+            let mut parts = split_cstrs(payload);
+            let name = parts.next().ok_or(MilterError::MalformedPayload)?;
+            let value = parts.next().unwrap_or_default();
+            Ok(MilterAction::AddHeader { name, value })
+        }
+        SMFIR_CHGFROM => {
+            let addr = split_cstrs(payload)
+                .next()
+                .ok_or(MilterError::MalformedPayload)?;
+            Ok(MilterAction::ChangeFrom(parse_path(&addr)?))
+        }
+        SMFIR_ADDRCPT | SMFIR_ADDRCPT_PAR => {
+            let addr = split_cstrs(payload)
+                .next()
+                .ok_or(MilterError::MalformedPayload)?;
+            Ok(MilterAction::AddRcpt(parse_path(&addr)?))
+        }
+        other => Err(MilterError::UnexpectedResponse(other as char)),
+    }
+}
+
+/// Build a `MAIL`/`RCPT` payload: the bracketed address followed by each ESMTP
+/// argument, every element NUL terminated.
+fn path_payload(mailbox: &MailBox, args: &[String]) -> Vec<u8> {
+    let mut payload = Vec::new();
+    push_cstr(&mut payload, &format!("<{}>", mailbox.email));
+    for arg in args {
+        push_cstr(&mut payload, arg);
+    }
+    payload
+}
+
+/// Parse an address sent by the filter, tolerating surrounding angle brackets.
+fn parse_path(value: &str) -> Result<MailBox, MilterError> {
+    let inner = value.trim_start_matches('<').trim_end_matches('>');
+    MailBox::try_from(inner).map_err(|_| MilterError::MalformedPayload)
+}
+
+/// Append a NUL-terminated string to a packet payload.
+fn push_cstr(buf: &mut Vec<u8>, value: &str) {
+    buf.extend_from_slice(value.as_bytes());
+    buf.push(0);
+}
+
+/// Iterate over the NUL-separated strings in a payload.
+fn split_cstrs(payload: &[u8]) -> impl Iterator<Item = String> + '_ {
+    payload
+        .split(|&b| b == 0)
+        .filter(|slice| !slice.is_empty())
+        .map(|slice| String::from_utf8_lossy(slice).into_owned())
+}
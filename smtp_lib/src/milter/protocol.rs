@@ -0,0 +1,64 @@
+/*!
+Wire constants for the Sendmail milter protocol: command codes, response codes,
+action flags (`SMFIF_*`) and protocol-step flags (`SMFIP_*`).
+
+Values mirror `libmilter/mfdef.h`.
+*/
+
+/// The protocol version this client speaks.
+pub const SMFI_PROT_VERSION: u32 = 6;
+
+/// The largest packet length (command byte plus payload) this client will accept, as
+/// a denial-of-service guard.
+pub const MAX_PACKET_LEN: usize = 1 << 20;
+
+// Command codes (client -> filter).
+pub const SMFIC_OPTNEG: u8 = b'O';
+pub const SMFIC_CONNECT: u8 = b'C';
+pub const SMFIC_HELO: u8 = b'H';
+pub const SMFIC_MAIL: u8 = b'M';
+pub const SMFIC_RCPT: u8 = b'R';
+pub const SMFIC_DATA: u8 = b'T';
+pub const SMFIC_HEADER: u8 = b'L';
+pub const SMFIC_EOH: u8 = b'N';
+pub const SMFIC_BODY: u8 = b'B';
+pub const SMFIC_BODYEOB: u8 = b'E';
+pub const SMFIC_QUIT: u8 = b'Q';
+pub const SMFIC_ABORT: u8 = b'A';
+
+// Response codes (filter -> client).
+pub const SMFIR_CONTINUE: u8 = b'c';
+pub const SMFIR_ACCEPT: u8 = b'a';
+pub const SMFIR_REJECT: u8 = b'r';
+pub const SMFIR_DISCARD: u8 = b'd';
+pub const SMFIR_TEMPFAIL: u8 = b't';
+pub const SMFIR_REPLBODY: u8 = b'b';
+pub const SMFIR_ADDHEADER: u8 = b'h';
+pub const SMFIR_CHGFROM: u8 = b'e';
+pub const SMFIR_ADDRCPT: u8 = b'+';
+pub const SMFIR_ADDRCPT_PAR: u8 = b'2';
+pub const SMFIR_REPLYCODE: u8 = b'y';
+pub const SMFIR_PROGRESS: u8 = b'p';
+
+// Action flags offered in SMFIC_OPTNEG (`SMFIF_*`).
+pub const SMFIF_ADDHDRS: u32 = 0x01;
+pub const SMFIF_CHGBODY: u32 = 0x02;
+pub const SMFIF_ADDRCPT: u32 = 0x04;
+pub const SMFIF_DELRCPT: u32 = 0x08;
+pub const SMFIF_CHGHDRS: u32 = 0x10;
+pub const SMFIF_CHGFROM: u32 = 0x40;
+pub const SMFIF_ADDRCPT_PAR: u32 = 0x80;
+
+/// The actions advertised by default: everything this client can apply.
+pub const SMFIF_DEFAULT: u32 =
+    SMFIF_ADDHDRS | SMFIF_CHGBODY | SMFIF_ADDRCPT | SMFIF_CHGFROM | SMFIF_ADDRCPT_PAR;
+
+// Protocol-step flags requested by the filter (`SMFIP_*`): when set, the client must
+// skip sending that checkpoint.
+pub const SMFIP_NOCONNECT: u32 = 0x01;
+pub const SMFIP_NOHELO: u32 = 0x02;
+pub const SMFIP_NOMAIL: u32 = 0x04;
+pub const SMFIP_NORCPT: u32 = 0x08;
+pub const SMFIP_NOBODY: u32 = 0x10;
+pub const SMFIP_NOHDRS: u32 = 0x20;
+pub const SMFIP_NOEOH: u32 = 0x40;
@@ -0,0 +1,359 @@
+/*!
+# Typed ESMTP Parameters
+
+[MailParameter] and [RcptParameter] model the optional `esmtp-param` tokens that may
+trail a `MAIL FROM` or `RCPT TO` command. They correspond to the service extensions
+advertised by [SMTPServerExtension](crate::smtp_server::SMTPServerExtension), so a
+client can negotiate against what a server announces instead of hand-formatting
+strings.
+
+The DSN parameters (`RET`, `ENVID`, `NOTIFY`, `ORCPT`) are defined in
+[RFC 3461](https://datatracker.ietf.org/doc/html/rfc3461); `SIZE` in
+[RFC 1870](https://datatracker.ietf.org/doc/html/rfc1870); `BODY` in
+[RFC 6152](https://datatracker.ietf.org/doc/html/rfc6152)/[RFC 3030]; `AUTH=` in
+[RFC 4954](https://datatracker.ietf.org/doc/html/rfc4954) and `SMTPUTF8` in
+[RFC 6531](https://datatracker.ietf.org/doc/html/rfc6531).
+
+[RFC 3030]: https://datatracker.ietf.org/doc/html/rfc3030
+*/
+use crate::command::CommandParseError;
+use mail_lib_types::MailBox;
+use std::fmt::Display;
+use std::str::FromStr;
+
+/// The `BODY=` content encoding ([8BITMIME]/[BINARYMIME]).
+///
+/// [8BITMIME]: https://datatracker.ietf.org/doc/html/rfc6152
+/// [BINARYMIME]: https://datatracker.ietf.org/doc/html/rfc3030
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BodyEncoding {
+    SevenBit,
+    EightBitMime,
+    BinaryMime,
+}
+impl Display for BodyEncoding {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let value = match self {
+            BodyEncoding::SevenBit => "7BIT",
+            BodyEncoding::EightBitMime => "8BITMIME",
+            BodyEncoding::BinaryMime => "BINARYMIME",
+        };
+        f.write_str(value)
+    }
+}
+impl FromStr for BodyEncoding {
+    type Err = CommandParseError;
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value.to_ascii_uppercase().as_str() {
+            "7BIT" => Ok(BodyEncoding::SevenBit),
+            "8BITMIME" => Ok(BodyEncoding::EightBitMime),
+            "BINARYMIME" => Ok(BodyEncoding::BinaryMime),
+            _ => Err(CommandParseError::InvalidArgument(value.to_owned())),
+        }
+    }
+}
+
+/// The `RET=` DSN parameter: return the `FULL` message or `HDRS` only.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FullOrHdrs {
+    Full,
+    Hdrs,
+}
+impl Display for FullOrHdrs {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            FullOrHdrs::Full => f.write_str("FULL"),
+            FullOrHdrs::Hdrs => f.write_str("HDRS"),
+        }
+    }
+}
+impl FromStr for FullOrHdrs {
+    type Err = CommandParseError;
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value.to_ascii_uppercase().as_str() {
+            "FULL" => Ok(FullOrHdrs::Full),
+            "HDRS" => Ok(FullOrHdrs::Hdrs),
+            _ => Err(CommandParseError::InvalidArgument(value.to_owned())),
+        }
+    }
+}
+
+/// A single `esmtp-param` on a `MAIL FROM` command.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum MailParameter {
+    /// `SIZE=<n>` — the declared message size in octets.
+    Size(u64),
+    /// `BODY=<encoding>`.
+    Body(BodyEncoding),
+    /// `AUTH=<mailbox>` — the authenticated identity, or `None` for `AUTH=<>`.
+    Auth(Option<MailBox>),
+    /// `SMTPUTF8`.
+    SmtpUtf8,
+    /// `RET=<FULL|HDRS>`.
+    Ret(FullOrHdrs),
+    /// `ENVID=<xtext>` — the DSN envelope identifier (decoded).
+    Envid(String),
+    /// Any unrecognised parameter, preserved verbatim.
+    Other { name: String, value: Option<String> },
+}
+impl Display for MailParameter {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            MailParameter::Size(size) => write!(f, "SIZE={}", size),
+            MailParameter::Body(encoding) => write!(f, "BODY={}", encoding),
+            MailParameter::Auth(Some(mailbox)) => {
+                write!(f, "AUTH={}", xtext_encode(&mailbox.email.to_string()))
+            }
+            MailParameter::Auth(None) => f.write_str("AUTH=<>"),
+            MailParameter::SmtpUtf8 => f.write_str("SMTPUTF8"),
+            MailParameter::Ret(ret) => write!(f, "RET={}", ret),
+            MailParameter::Envid(envid) => write!(f, "ENVID={}", xtext_encode(envid)),
+            MailParameter::Other { name, value } => match value {
+                Some(value) => write!(f, "{}={}", name, value),
+                None => f.write_str(name),
+            },
+        }
+    }
+}
+impl FromStr for MailParameter {
+    type Err = CommandParseError;
+    fn from_str(token: &str) -> Result<Self, Self::Err> {
+        let (keyword, value) = split_param(token);
+        match keyword.to_ascii_uppercase().as_str() {
+            "SIZE" => Ok(MailParameter::Size(
+                require_value(keyword, value)?
+                    .parse()
+                    .map_err(|_| CommandParseError::InvalidArgument(token.to_owned()))?,
+            )),
+            "BODY" => Ok(MailParameter::Body(require_value(keyword, value)?.parse()?)),
+            "AUTH" => {
+                let value = require_value(keyword, value)?;
+                let mailbox = if value == "<>" {
+                    None
+                } else {
+                    let decoded = xtext_decode(value)?;
+                    let inner = decoded.trim_start_matches('<').trim_end_matches('>');
+                    Some(
+                        MailBox::try_from(inner)
+                            .map_err(|_| CommandParseError::InvalidMailBox(inner.to_owned()))?,
+                    )
+                };
+                Ok(MailParameter::Auth(mailbox))
+            }
+            "SMTPUTF8" => Ok(MailParameter::SmtpUtf8),
+            "RET" => Ok(MailParameter::Ret(require_value(keyword, value)?.parse()?)),
+            "ENVID" => Ok(MailParameter::Envid(xtext_decode(require_value(
+                keyword, value,
+            )?)?)),
+            _ => Ok(MailParameter::Other {
+                name: keyword.to_owned(),
+                value: value.map(|v| v.to_owned()),
+            }),
+        }
+    }
+}
+
+/// One of the `NOTIFY=` conditions ([RFC 3461]).
+///
+/// [RFC 3461]: https://datatracker.ietf.org/doc/html/rfc3461
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NotifyKind {
+    Never,
+    Success,
+    Failure,
+    Delay,
+}
+impl Display for NotifyKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let value = match self {
+            NotifyKind::Never => "NEVER",
+            NotifyKind::Success => "SUCCESS",
+            NotifyKind::Failure => "FAILURE",
+            NotifyKind::Delay => "DELAY",
+        };
+        f.write_str(value)
+    }
+}
+impl FromStr for NotifyKind {
+    type Err = CommandParseError;
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value.to_ascii_uppercase().as_str() {
+            "NEVER" => Ok(NotifyKind::Never),
+            "SUCCESS" => Ok(NotifyKind::Success),
+            "FAILURE" => Ok(NotifyKind::Failure),
+            "DELAY" => Ok(NotifyKind::Delay),
+            _ => Err(CommandParseError::InvalidArgument(value.to_owned())),
+        }
+    }
+}
+
+/// A single `esmtp-param` on a `RCPT TO` command.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RcptParameter {
+    /// `NOTIFY=<conditions>` — a comma separated list of [NotifyKind]s.
+    Notify(Vec<NotifyKind>),
+    /// `ORCPT=<addr-type>;<addr>` — the original recipient (address decoded).
+    Orcpt { addr_type: String, addr: String },
+    /// Any unrecognised parameter, preserved verbatim.
+    Other { name: String, value: Option<String> },
+}
+impl Display for RcptParameter {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            RcptParameter::Notify(kinds) => {
+                f.write_str("NOTIFY=")?;
+                for (index, kind) in kinds.iter().enumerate() {
+                    if index > 0 {
+                        f.write_str(",")?;
+                    }
+                    write!(f, "{}", kind)?;
+                }
+                Ok(())
+            }
+            RcptParameter::Orcpt { addr_type, addr } => {
+                write!(f, "ORCPT={};{}", addr_type, xtext_encode(addr))
+            }
+            RcptParameter::Other { name, value } => match value {
+                Some(value) => write!(f, "{}={}", name, value),
+                None => f.write_str(name),
+            },
+        }
+    }
+}
+impl FromStr for RcptParameter {
+    type Err = CommandParseError;
+    fn from_str(token: &str) -> Result<Self, Self::Err> {
+        let (keyword, value) = split_param(token);
+        match keyword.to_ascii_uppercase().as_str() {
+            "NOTIFY" => {
+                let value = require_value(keyword, value)?;
+                let kinds = value
+                    .split(',')
+                    .map(NotifyKind::from_str)
+                    .collect::<Result<Vec<_>, _>>()?;
+                // NEVER is mutually exclusive with any other keyword (RFC 3461 §4.1).
+                if kinds.contains(&NotifyKind::Never) && kinds.len() > 1 {
+                    return Err(CommandParseError::InvalidArgument(token.to_owned()));
+                }
+                Ok(RcptParameter::Notify(kinds))
+            }
+            "ORCPT" => {
+                let value = require_value(keyword, value)?;
+                let (addr_type, addr) = value
+                    .split_once(';')
+                    .ok_or_else(|| CommandParseError::InvalidArgument(token.to_owned()))?;
+                Ok(RcptParameter::Orcpt {
+                    addr_type: addr_type.to_owned(),
+                    addr: xtext_decode(addr)?,
+                })
+            }
+            _ => Ok(RcptParameter::Other {
+                name: keyword.to_owned(),
+                value: value.map(|v| v.to_owned()),
+            }),
+        }
+    }
+}
+
+/// Split a parameter token on its first `=`.
+fn split_param(token: &str) -> (&str, Option<&str>) {
+    match token.split_once('=') {
+        Some((keyword, value)) => (keyword, Some(value)),
+        None => (token, None),
+    }
+}
+
+fn require_value<'a>(
+    keyword: &str,
+    value: Option<&'a str>,
+) -> Result<&'a str, CommandParseError> {
+    value.ok_or_else(|| CommandParseError::InvalidArgument(keyword.to_owned()))
+}
+
+/// Decode an `xtext` string per [RFC 3461 §4]: `+XX` hex escapes, everything else
+/// literal.
+fn xtext_decode(value: &str) -> Result<String, CommandParseError> {
+    let mut out = String::with_capacity(value.len());
+    let mut bytes = value.bytes();
+    while let Some(byte) = bytes.next() {
+        if byte == b'+' {
+            let high = bytes.next();
+            let low = bytes.next();
+            match (high, low) {
+                (Some(high), Some(low)) => {
+                    let decoded = (hex_value(high)? << 4) | hex_value(low)?;
+                    out.push(decoded as char);
+                }
+                _ => return Err(CommandParseError::InvalidArgument(value.to_owned())),
+            }
+        } else {
+            out.push(byte as char);
+        }
+    }
+    Ok(out)
+}
+
+fn hex_value(byte: u8) -> Result<u8, CommandParseError> {
+    match byte {
+        b'0'..=b'9' => Ok(byte - b'0'),
+        b'A'..=b'F' => Ok(byte - b'A' + 10),
+        b'a'..=b'f' => Ok(byte - b'a' + 10),
+        _ => Err(CommandParseError::InvalidArgument((byte as char).to_string())),
+    }
+}
+
+/// Encode a string as `xtext`: printable ASCII in `!`..=`~` except `+` and `=` is
+/// passed through, everything else is `+XX` escaped.
+fn xtext_encode(value: &str) -> String {
+    let mut out = String::with_capacity(value.len());
+    for byte in value.bytes() {
+        if (0x21..=0x7e).contains(&byte) && byte != b'+' && byte != b'=' {
+            out.push(byte as char);
+        } else {
+            out.push_str(&format!("+{:02X}", byte));
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{MailParameter, RcptParameter};
+
+    /// Parameters must survive a parse / [Display] / parse round trip.
+    fn assert_mail_round_trip(token: &str) {
+        let param: MailParameter = token.parse().expect("token should parse");
+        let reparsed: MailParameter = param.to_string().parse().expect("re-emitted token should parse");
+        assert_eq!(param, reparsed);
+    }
+
+    #[test]
+    fn mail_parameters_round_trip() {
+        for token in ["SIZE=2048", "BODY=BINARYMIME", "SMTPUTF8", "RET=HDRS", "AUTH=<>"] {
+            assert_mail_round_trip(token);
+        }
+    }
+
+    #[test]
+    fn auth_mailbox_is_xtext_encoded() {
+        let param: MailParameter = "AUTH=alice@example.com".parse().unwrap();
+        // An address is reproduced verbatim when it contains only printable ASCII
+        // other than `+` and `=`.
+        assert_eq!(param.to_string(), "AUTH=alice@example.com");
+        // The decoded form round-trips back through xtext.
+        assert_eq!(param, param.to_string().parse().unwrap());
+    }
+
+    #[test]
+    fn envid_round_trips_through_xtext() {
+        let param: MailParameter = "ENVID=a+2Bb".parse().unwrap();
+        assert_eq!(param, param.to_string().parse().unwrap());
+    }
+
+    #[test]
+    fn notify_never_is_exclusive() {
+        assert!("NOTIFY=SUCCESS,FAILURE".parse::<RcptParameter>().is_ok());
+        assert!("NOTIFY=NEVER".parse::<RcptParameter>().is_ok());
+        assert!("NOTIFY=NEVER,SUCCESS".parse::<RcptParameter>().is_err());
+    }
+}
@@ -1,3 +1,6 @@
+use crate::command::Command;
+use crate::error::SMTPError;
+use crate::milter::MilterAction;
 use crate::SMTPConnectionState;
 use common::credentials::LoginMechanism;
 use enum_helper::EnumOfKeys;
@@ -30,6 +33,16 @@ pub enum SMTPServerExtension {
     Size(u64),
     StartTLS,
     Auth(Vec<LoginMechanism>),
+    Pipelining,
+    #[enum_attr(strum(serialize = "8BITMIME"))]
+    EightBitMime,
+    SmtpUtf8,
+    EnhancedStatusCodes,
+    Chunking,
+    BinaryMime,
+    Dsn,
+    DeliverBy(Option<u64>),
+    Help,
     #[enum_of_keys(default=name)]
     #[enum_attr(strum(default))]
     Other{
@@ -45,6 +58,16 @@ impl Display for SMTPServerExtension {
             SMTPServerExtension::Auth(value) => {
                 write!(f, "AUTH {}", LoginMechanism::format_iter(value.iter()))
             }
+            SMTPServerExtension::Pipelining => write!(f, "PIPELINING"),
+            SMTPServerExtension::EightBitMime => write!(f, "8BITMIME"),
+            SMTPServerExtension::SmtpUtf8 => write!(f, "SMTPUTF8"),
+            SMTPServerExtension::EnhancedStatusCodes => write!(f, "ENHANCEDSTATUSCODES"),
+            SMTPServerExtension::Chunking => write!(f, "CHUNKING"),
+            SMTPServerExtension::BinaryMime => write!(f, "BINARYMIME"),
+            SMTPServerExtension::Dsn => write!(f, "DSN"),
+            SMTPServerExtension::DeliverBy(Some(min_by)) => write!(f, "DELIVERBY {}", min_by),
+            SMTPServerExtension::DeliverBy(None) => write!(f, "DELIVERBY"),
+            SMTPServerExtension::Help => write!(f, "HELP"),
             SMTPServerExtension::Other{
                 name,
                 value,
@@ -76,8 +99,24 @@ impl TryFrom<String> for SMTPServerExtension {
                 .map_err(|_| ServerExtensionParseError::InvalidSize(value.clone()))?;
                 Ok(Self::Size(size))
             }
-            "STARTLS" => Ok(Self::StartTLS),
+            "STARTTLS" => Ok(Self::StartTLS),
             "AUTH" => Ok(Self::Auth(LoginMechanism::from_iter(split))),
+            "PIPELINING" => Ok(Self::Pipelining),
+            "8BITMIME" => Ok(Self::EightBitMime),
+            "SMTPUTF8" => Ok(Self::SmtpUtf8),
+            "ENHANCEDSTATUSCODES" => Ok(Self::EnhancedStatusCodes),
+            "CHUNKING" => Ok(Self::Chunking),
+            "BINARYMIME" => Ok(Self::BinaryMime),
+            "DSN" => Ok(Self::Dsn),
+            "DELIVERBY" => {
+                let min_by = split
+                    .next()
+                    .map(u64::from_str)
+                    .transpose()
+                    .map_err(|_| ServerExtensionParseError::InvalidExtension(value.clone()))?;
+                Ok(Self::DeliverBy(min_by))
+            }
+            "HELP" => Ok(Self::Help),
             other_key => {
                 let other_key = other_key.to_string();
                 let other_data = value.splitn(2, " ").nth(1).map(|s| s.to_string());
@@ -98,6 +137,27 @@ pub trait SMTPServer: Debug {
     fn get_greeting(&self) -> Option<&str>;
 
     fn supported_extensions(&self) -> &Vec<SMTPServerExtension>;
+
+    /// Whether the server advertised the given extension.
+    fn supports(&self, key: SMTPServerExtensionKeys) -> bool {
+        self.supported_extensions()
+            .iter()
+            .any(|extension| SMTPServerExtensionKeys::from(extension) == key)
+    }
+
+    /// The maximum message size the server will accept, if it advertised `SIZE`.
+    ///
+    /// A declared size of `0` means the server imposes no fixed limit, so it is
+    /// reported as `None`.
+    fn max_message_size(&self) -> Option<u64> {
+        self.supported_extensions().iter().find_map(|extension| {
+            match extension {
+                SMTPServerExtension::Size(0) => None,
+                SMTPServerExtension::Size(size) => Some(*size),
+                _ => None,
+            }
+        })
+    }
 }
 
 pub trait SMTPConnection: Debug {
@@ -110,6 +170,39 @@ pub trait SMTPConnection: Debug {
     fn set_state(&mut self, state: SMTPConnectionState);
 
     fn get_end_of_multiline_command(&self) -> &str;
+
+    /// Validate `command` against the current connection state and advance to the
+    /// next state.
+    ///
+    /// Out-of-order commands are rejected with a 503 "bad sequence of commands" error
+    /// and the state is left untouched. `RSET` returns the connection to
+    /// [`Greeted`](SMTPConnectionState::Greeted) (or leaves it at
+    /// [`Connected`](SMTPConnectionState::Connected) before greeting); implementors should also clear their
+    /// transaction buffers when this method reports a successful `RSET` transition.
+    fn try_transition(&mut self, command: &Command) -> Result<(), SMTPError> {
+        let next = self.get_state().transition(command)?;
+        self.set_state(next);
+        Ok(())
+    }
+
+    /// Advance the state machine after consulting a milter at this checkpoint.
+    ///
+    /// `decision` is the [MilterAction] returned by
+    /// [`MilterClient::check`](crate::milter::MilterClient::check) for `command`. It is
+    /// run through [`MilterAction::enforce`] *before* the transition, so a filter
+    /// `Reject`/`TempFail` at `MAIL`/`RCPT`/`DATA` surfaces as a 550/451 and the
+    /// command never advances the state — a reject at `RCPT`, for instance, is
+    /// reported before `DATA` is accepted. Any non-terminal action
+    /// (`AddHeader`, `ChangeFrom`, ...) is returned to the caller to apply.
+    fn try_transition_filtered(
+        &mut self,
+        command: &Command,
+        decision: MilterAction,
+    ) -> Result<MilterAction, SMTPError> {
+        let action = decision.enforce()?;
+        self.try_transition(command)?;
+        Ok(action)
+    }
 }
 
 pub mod async_traits {